@@ -1,14 +1,15 @@
 #![allow(unused)]
-use byteorder::{NativeEndian, ReadBytesExt};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
 use object::read::elf::{FileHeader, ProgramHeader};
-use object::{elf, Endianness};
-use sentry_backtrace::{Frame, Stacktrace};
+use object::{elf, Endianness, Object, ObjectSegment, ObjectSymbol};
+use sentry_backtrace::{Frame, RegVal, Stacktrace};
+use serde::Serialize;
 use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt::{Debug, Display};
 use std::fs;
-use std::io::{BufRead, Cursor};
-use std::path::Path;
+use std::io::{BufRead, Cursor, Read};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 enum CoredumpError {
@@ -16,6 +17,7 @@ enum CoredumpError {
     MissingDataSection,
     SymbolizationFailed,
     NotCoreFile,
+    DecompressionFailed,
 }
 impl Display for CoredumpError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -25,7 +27,7 @@ impl Display for CoredumpError {
 
 impl Error for CoredumpError {}
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 struct Stackframe {
     start: u64,
     end: u64,
@@ -42,10 +44,14 @@ impl Display for Stackframe {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 struct CoredumpNotesHeader {
     page_size: u64,
     frames: Vec<Stackframe>,
+    threads: Vec<ThreadState>,
+    process: Option<ProcessInfo>,
+    #[serde(skip)]
+    memory: CoreMemory,
 }
 
 impl CoredumpNotesHeader {
@@ -53,6 +59,9 @@ impl CoredumpNotesHeader {
         Self {
             page_size,
             frames: Vec::new(),
+            threads: Vec::new(),
+            process: None,
+            memory: CoreMemory::default(),
         }
     }
     fn add_frame(&mut self, frame: Stackframe) {
@@ -65,10 +74,118 @@ impl Display for CoredumpNotesHeader {
         for frame in &self.frames {
             writeln!(f, "{}", frame)?;
         }
+        for (i, thread) in self.threads.iter().enumerate() {
+            writeln!(f, "Thread {}: {} registers", i, thread.registers.len())?;
+        }
         Ok(())
     }
 }
 
+/// Register set captured from a single NT_PRSTATUS note, i.e. one thread's
+/// state at the time of the crash.
+#[derive(Debug, Default, Clone, Serialize)]
+struct ThreadState {
+    registers: BTreeMap<String, u64>,
+}
+
+/// Process identity captured from the core's NT_PRPSINFO note.
+#[derive(Debug, Default, Clone, Serialize)]
+struct ProcessInfo {
+    pid: i32,
+    command: String,
+}
+
+impl ThreadState {
+    fn reg(&self, name: &str) -> Option<u64> {
+        self.registers.get(name).copied()
+    }
+    fn instruction_pointer(&self) -> Option<u64> {
+        self.reg("rip")
+    }
+    fn stack_pointer(&self) -> Option<u64> {
+        self.reg("rsp")
+    }
+    fn frame_pointer(&self) -> Option<u64> {
+        self.reg("rbp")
+    }
+
+    /// Converts the register set into the `RegVal` map expected by
+    /// `Stacktrace.registers`.
+    fn as_reg_vals(&self) -> BTreeMap<String, RegVal> {
+        self.registers
+            .iter()
+            .map(|(name, value)| (name.clone(), RegVal(*value)))
+            .collect()
+    }
+}
+
+/// Order of the general-purpose registers within `elf_prstatus.pr_reg` on
+/// x86_64, starting at byte offset 112 of the NT_PRSTATUS descriptor.
+const X86_64_PRSTATUS_REGS: [&str; 27] = [
+    "r15", "r14", "r13", "r12", "rbp", "rbx", "r11", "r10", "r9", "r8", "rax", "rcx", "rdx",
+    "rsi", "rdi", "orig_rax", "rip", "cs", "eflags", "rsp", "ss", "fs_base", "gs_base", "ds",
+    "es", "fs", "gs",
+];
+const PRSTATUS_PR_REG_OFFSET: u64 = 112;
+
+/// A single PT_LOAD segment: the mapping of core file bytes to the
+/// process's virtual address space at crash time.
+#[derive(Debug, Clone)]
+struct MemorySegment {
+    vaddr: u64,
+    file_offset: u64,
+    file_size: u64,
+    mem_size: u64,
+}
+
+impl MemorySegment {
+    fn contains(&self, vaddr: u64) -> bool {
+        vaddr >= self.vaddr && vaddr < self.vaddr + self.mem_size
+    }
+}
+
+/// A model of the crashed process's memory built from the core's PT_LOAD
+/// program headers, letting callers read process memory out of the core
+/// file by virtual address.
+#[derive(Debug, Clone)]
+struct CoreMemory {
+    segments: Vec<MemorySegment>,
+    endian: Endianness,
+}
+
+impl Default for CoreMemory {
+    fn default() -> Self {
+        Self {
+            segments: Vec::new(),
+            endian: Endianness::Little,
+        }
+    }
+}
+
+impl CoreMemory {
+    fn contains(&self, vaddr: u64) -> bool {
+        self.segments.iter().any(|segment| segment.contains(vaddr))
+    }
+
+    /// Reads a `u64` at `vaddr` using the core's own endianness, treating
+    /// bytes beyond a segment's `file_size` as zero-filled (BSS).
+    fn read_u64(&self, core_data: &[u8], vaddr: u64) -> Option<u64> {
+        let segment = self.segments.iter().find(|segment| segment.contains(vaddr))?;
+        let rel = vaddr - segment.vaddr;
+        let mut bytes = [0u8; 8];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            let byte_rel = rel + i as u64;
+            if byte_rel < segment.file_size {
+                *byte = *core_data.get((segment.file_offset + byte_rel) as usize)?;
+            }
+        }
+        Some(match self.endian {
+            Endianness::Little => u64::from_le_bytes(bytes),
+            Endianness::Big => u64::from_be_bytes(bytes),
+        })
+    }
+}
+
 fn parse_backtrace_notes<Elf>(
     endian: Elf::Endian,
     bt: &object::read::elf::Note<Elf>,
@@ -77,17 +194,17 @@ where
     Elf: FileHeader<Endian = Endianness>,
 {
     let mut cursor = Cursor::new(bt.desc());
-    let number_of_entries = cursor.read_u64::<NativeEndian>()?;
-    let page_size = cursor.read_u64::<NativeEndian>()?;
+    let number_of_entries = read_u64_endian(&mut cursor, endian)?;
+    let page_size = read_u64_endian(&mut cursor, endian)?;
 
     let mut name_cursor = Cursor::new(bt.desc());
     name_cursor.set_position(cursor.position() + number_of_entries * 24);
 
     let mut headers = CoredumpNotesHeader::new(page_size);
     for name in name_cursor.split(0).flatten() {
-        let start = cursor.read_u64::<NativeEndian>()?;
-        let end = cursor.read_u64::<NativeEndian>()?;
-        let offset = cursor.read_u64::<NativeEndian>()?;
+        let start = read_u64_endian(&mut cursor, endian)?;
+        let end = read_u64_endian(&mut cursor, endian)?;
+        let offset = read_u64_endian(&mut cursor, endian)?;
         let name = String::from_utf8_lossy(&name).into_owned();
 
         headers.add_frame(Stackframe {
@@ -101,6 +218,75 @@ where
     Ok(headers)
 }
 
+/// Reads a `u64` off `cursor` using the core file's actual endianness
+/// rather than the host's.
+fn read_u64_endian(cursor: &mut Cursor<&[u8]>, endian: Endianness) -> Result<u64, Box<dyn Error>> {
+    Ok(match endian {
+        Endianness::Little => cursor.read_u64::<LittleEndian>()?,
+        Endianness::Big => cursor.read_u64::<BigEndian>()?,
+    })
+}
+
+/// Reads an `i32` off `cursor` using the core file's actual endianness
+/// rather than the host's.
+fn read_i32_endian(cursor: &mut Cursor<&[u8]>, endian: Endianness) -> Result<i32, Box<dyn Error>> {
+    Ok(match endian {
+        Endianness::Little => cursor.read_i32::<LittleEndian>()?,
+        Endianness::Big => cursor.read_i32::<BigEndian>()?,
+    })
+}
+
+/// Parses a single NT_PRSTATUS note's `elf_prstatus` descriptor into a
+/// thread's register set.
+fn parse_prstatus_note<Elf>(
+    endian: Elf::Endian,
+    bt: &object::read::elf::Note<Elf>,
+) -> Result<ThreadState, Box<dyn Error>>
+where
+    Elf: FileHeader<Endian = Endianness>,
+{
+    let mut cursor = Cursor::new(bt.desc());
+    cursor.set_position(PRSTATUS_PR_REG_OFFSET);
+
+    let mut registers = BTreeMap::new();
+    for name in X86_64_PRSTATUS_REGS {
+        let value = read_u64_endian(&mut cursor, endian)?;
+        registers.insert(name.to_string(), value);
+    }
+
+    Ok(ThreadState { registers })
+}
+
+/// Byte offsets of the fields we care about within `elf_prpsinfo`'s
+/// NT_PRPSINFO descriptor (64-bit layout: four `char` fields, padding to
+/// align `pr_flag`, then the `uid`/`gid`/`pid`/`ppid`/`pgrp`/`sid` ints).
+const PRPSINFO_PID_OFFSET: u64 = 24;
+const PRPSINFO_FNAME_OFFSET: usize = 40;
+const PRPSINFO_FNAME_LEN: usize = 16;
+
+/// Parses a single NT_PRPSINFO note's `elf_prpsinfo` descriptor into the
+/// crashed process's pid and command name.
+fn parse_prpsinfo_note<Elf>(
+    endian: Elf::Endian,
+    bt: &object::read::elf::Note<Elf>,
+) -> Result<ProcessInfo, Box<dyn Error>>
+where
+    Elf: FileHeader<Endian = Endianness>,
+{
+    let desc = bt.desc();
+
+    let mut cursor = Cursor::new(desc);
+    cursor.set_position(PRPSINFO_PID_OFFSET);
+    let pid = read_i32_endian(&mut cursor, endian)?;
+
+    let fname_end = (PRPSINFO_FNAME_OFFSET + PRPSINFO_FNAME_LEN).min(desc.len());
+    let command = String::from_utf8_lossy(&desc[PRPSINFO_FNAME_OFFSET..fname_end])
+        .trim_end_matches('\0')
+        .to_string();
+
+    Ok(ProcessInfo { pid, command })
+}
+
 fn read_frames<Elf: FileHeader<Endian = Endianness>>(
     object: &[u8],
 ) -> Result<CoredumpNotesHeader, Box<dyn Error>> {
@@ -111,51 +297,514 @@ fn read_frames<Elf: FileHeader<Endian = Endianness>>(
         Err(CoredumpError::NotCoreFile)?;
     }
 
+    let mut mappings = None;
+    let mut threads = Vec::new();
+    let mut segments = Vec::new();
+    let mut process = None;
+
     for header in elf.program_headers(endian, object)? {
-        if header.p_type(endian) == elf::PT_NOTE {
-            if let Ok(Some(mut notes)) = header.notes(endian, object) {
-                while let Ok(Some(note)) = notes.next() {
-                    if note.n_type(endian) == elf::NT_FILE {
-                        let notes = parse_backtrace_notes(endian, &note)?;
-                        return Ok(notes);
+        match header.p_type(endian) {
+            elf::PT_NOTE => {
+                if let Ok(Some(mut notes)) = header.notes(endian, object) {
+                    while let Ok(Some(note)) = notes.next() {
+                        match note.n_type(endian) {
+                            elf::NT_FILE => {
+                                mappings = Some(parse_backtrace_notes(endian, &note)?);
+                            }
+                            elf::NT_PRSTATUS => {
+                                threads.push(parse_prstatus_note(endian, &note)?);
+                            }
+                            elf::NT_PRPSINFO => {
+                                process = Some(parse_prpsinfo_note(endian, &note)?);
+                            }
+                            _ => {}
+                        }
                     }
                 }
             }
+            elf::PT_LOAD => {
+                segments.push(MemorySegment {
+                    vaddr: header.p_vaddr(endian).into(),
+                    file_offset: header.p_offset(endian).into(),
+                    file_size: header.p_filesz(endian).into(),
+                    mem_size: header.p_memsz(endian).into(),
+                });
+            }
+            _ => {}
         }
     }
-    Err(CoredumpError::MissingDataSection)?
+
+    let mut notes = mappings.ok_or(CoredumpError::MissingDataSection)?;
+    notes.threads = threads;
+    notes.memory = CoreMemory { segments, endian };
+    notes.process = process;
+    Ok(notes)
+}
+
+/// Walks the stack starting from `rip`/`rbp` assuming the crashed code was
+/// compiled with frame pointers: `*rbp` is the caller's saved `rbp` and
+/// `*(rbp+8)` is the return address. Stops when `rbp` is null, fails to
+/// increase (guards against cycles on a corrupt stack), or falls outside
+/// any mapped segment.
+fn unwind_frame_pointers(memory: &CoreMemory, core_data: &[u8], rip: u64, rbp: u64) -> Vec<u64> {
+    let mut addrs = vec![rip];
+    let mut rbp = rbp;
+
+    while rbp != 0 && memory.contains(rbp) {
+        let saved_rbp = match memory.read_u64(core_data, rbp) {
+            Some(value) => value,
+            None => break,
+        };
+        let return_addr = match memory.read_u64(core_data, rbp + 8) {
+            Some(value) => value,
+            None => break,
+        };
+        addrs.push(return_addr);
+
+        if saved_rbp <= rbp {
+            break;
+        }
+        rbp = saved_rbp;
+    }
+
+    addrs
+}
+
+/// Finds the mapping from `notes` whose `[start, end)` range contains `addr`.
+fn mapping_for_addr(notes: &CoredumpNotesHeader, addr: u64) -> Option<&Stackframe> {
+    notes
+        .frames
+        .iter()
+        .find(|mapping| addr >= mapping.start && addr < mapping.end)
+}
+
+/// Translates a file offset into the mapped object into the ELF virtual
+/// address space used by its symbol tables, by locating the PT_LOAD segment
+/// that covers the offset.
+fn vaddr_for_file_offset(object: &object::File, file_offset: u64) -> Option<u64> {
+    object.segments().find_map(|segment| {
+        let (seg_offset, seg_size) = segment.file_range();
+        if file_offset >= seg_offset && file_offset < seg_offset + seg_size {
+            Some(segment.address() + (file_offset - seg_offset))
+        } else {
+            None
+        }
+    })
+}
+
+/// The default search path for companion debug files, matching the layout
+/// used by distro packages (`<dir>/.build-id/<xx>/<rest>.debug`).
+const DEFAULT_DEBUG_DIRS: &[&str] = &["/usr/lib/debug"];
+
+fn default_debug_dirs() -> Vec<PathBuf> {
+    DEFAULT_DEBUG_DIRS.iter().map(PathBuf::from).collect()
+}
+
+/// Reads `.symtab` then `.dynsym` out of `object` as `(address, size, name)`
+/// triples, so results from a debug file can be merged with the original.
+fn symbol_table(object: &object::File) -> Vec<(u64, u64, String)> {
+    object
+        .symbols()
+        .chain(object.dynamic_symbols())
+        .filter_map(|symbol| {
+            let name = symbol.name().ok()?.to_string();
+            Some((symbol.address(), symbol.size().max(1), name))
+        })
+        .collect()
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
 }
 
-fn symbolicate_notes(notes: CoredumpNotesHeader) -> Result<Vec<Frame>, Box<dyn Error>> {
-    todo!("Symbolize me!");
-    Err(CoredumpError::SymbolizationFailed)?
+/// Builds the standard `.build-id` debug file path for `build_id` under
+/// `debug_dir`, e.g. `ab/cdef....debug` for build-id `abcdef...`.
+fn build_id_debug_path(debug_dir: &Path, build_id: &[u8]) -> Option<PathBuf> {
+    let (prefix, rest) = build_id.split_first()?;
+    Some(
+        debug_dir
+            .join(".build-id")
+            .join(format!("{:02x}", prefix))
+            .join(format!("{}.debug", hex_encode(rest))),
+    )
 }
 
-fn parse_coredump<P: AsRef<Path>>(path: P) -> Result<Stacktrace, Box<dyn Error>> {
-    let bin_data = fs::read(path)?;
+/// Reads the mapped file at `path` and returns its GNU build-id as a hex
+/// string, for inclusion in the crash event's image list.
+fn build_id_hex(path: &str) -> Option<String> {
+    let data = fs::read(path).ok()?;
+    let object = object::File::parse(&*data).ok()?;
+    let build_id = object.build_id().ok().flatten()?;
+    Some(hex_encode(build_id))
+}
+
+/// Looks up the external debug file matching `object`'s GNU build-id in
+/// `debug_dirs` and returns its symbol table, for stripped binaries whose
+/// symbols ship separately.
+fn debug_symbol_table(object: &object::File, debug_dirs: &[PathBuf]) -> Option<Vec<(u64, u64, String)>> {
+    let build_id = object.build_id().ok().flatten()?;
+    let debug_path = debug_dirs
+        .iter()
+        .find_map(|dir| build_id_debug_path(dir, build_id).filter(|path| path.is_file()))?;
+    let data = fs::read(debug_path).ok()?;
+    let debug_object = object::File::parse(&*data).ok()?;
+    Some(symbol_table(&debug_object))
+}
+
+/// Searches `.symtab` then `.dynsym` for the symbol whose
+/// `st_value..st_value+st_size` interval contains `vaddr`, falling back to a
+/// build-id-matched debug file when `object` has no `.symtab` of its own.
+fn symbol_for_vaddr(object: &object::File, debug_dirs: &[PathBuf], vaddr: u64) -> Option<String> {
+    let mut symbols = symbol_table(object);
+    if object.symbols().next().is_none() {
+        if let Some(debug_symbols) = debug_symbol_table(object, debug_dirs) {
+            symbols.extend(debug_symbols);
+        }
+    }
+    symbols
+        .into_iter()
+        .find(|(addr, size, _)| vaddr >= *addr && vaddr < addr + size)
+        .map(|(_, _, name)| name)
+}
+
+/// Resolves a single instruction address to a function name by finding the
+/// mapping that covers it, opening the mapped file, and searching its symbol
+/// tables for a match. Falls back to `0x<addr>+<mapping_name>` when the
+/// mapping is known but no symbol covers the address.
+fn symbolicate_addr(notes: &CoredumpNotesHeader, debug_dirs: &[PathBuf], addr: u64) -> Frame {
+    let mapping = match mapping_for_addr(notes, addr) {
+        Some(mapping) => mapping,
+        None => {
+            return Frame {
+                function: Some(format!("0x{:x}", addr)),
+                instruction_addr: Some(addr.into()),
+                ..Default::default()
+            }
+        }
+    };
+
+    let symbol = fs::read(&mapping.name).ok().and_then(|data| {
+        let object = object::File::parse(&*data).ok()?;
+        let file_offset = (addr - mapping.start) + mapping.offset * notes.page_size;
+        let vaddr = vaddr_for_file_offset(&object, file_offset)?;
+        symbol_for_vaddr(&object, debug_dirs, vaddr)
+    });
+
+    Frame {
+        function: Some(symbol.unwrap_or_else(|| format!("0x{:x}+{}", addr, mapping.name))),
+        instruction_addr: Some(addr.into()),
+        filename: Some(mapping.name.clone()),
+        package: Some(mapping.name.clone()),
+        in_app: Some(is_in_app_image(&mapping.name)),
+        ..Default::default()
+    }
+}
+
+/// Heuristic for Sentry's `in_app` flag: images under the usual system
+/// library locations (or a build-id-matched debug file) are framework code,
+/// not the application being debugged.
+fn is_in_app_image(image_path: &str) -> bool {
+    !(image_path.starts_with("/usr/lib")
+        || image_path.starts_with("/lib")
+        || image_path.contains("/.build-id/"))
+}
+
+fn symbolicate_notes(
+    notes: &CoredumpNotesHeader,
+    addresses: &[u64],
+    debug_dirs: &[PathBuf],
+) -> Result<Vec<Frame>, Box<dyn Error>> {
+    if addresses.is_empty() {
+        return Err(CoredumpError::SymbolizationFailed)?;
+    }
+    Ok(addresses
+        .iter()
+        .map(|&addr| symbolicate_addr(notes, debug_dirs, addr))
+        .collect())
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZLIB_MAGIC_FIRST_BYTE: u8 = 0x78;
+const ZLIB_MAGIC_SECOND_BYTES: [u8; 3] = [0x01, 0x9c, 0xda];
+
+/// Sniffs `data` for a gzip or zlib header and transparently inflates it,
+/// so callers can point the parser at the compressed cores that many crash
+/// collectors store. Uncompressed data is passed through unchanged.
+fn maybe_decompress(data: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let is_gzip = data.starts_with(&GZIP_MAGIC);
+    let is_zlib = data.len() >= 2
+        && data[0] == ZLIB_MAGIC_FIRST_BYTE
+        && ZLIB_MAGIC_SECOND_BYTES.contains(&data[1]);
+
+    if !is_gzip && !is_zlib {
+        return Ok(data);
+    }
+
+    let mut out = Vec::new();
+    let result = if is_gzip {
+        flate2::read::GzDecoder::new(&data[..]).read_to_end(&mut out)
+    } else {
+        flate2::read::ZlibDecoder::new(&data[..]).read_to_end(&mut out)
+    };
+    result.map_err(|_| CoredumpError::DecompressionFailed)?;
+    Ok(out)
+}
+
+/// Reads, decompresses, and parses a core file into its notes, returning
+/// the owned core bytes alongside so callers can walk process memory.
+fn load_core<P: AsRef<Path>>(path: P) -> Result<(Vec<u8>, CoredumpNotesHeader), Box<dyn Error>> {
+    let bin_data = maybe_decompress(fs::read(path)?)?;
     let file_kind = object::FileKind::parse(&*bin_data)?;
     let notes = match file_kind {
         object::FileKind::Elf64 => read_frames::<elf::FileHeader64<Endianness>>(&bin_data),
         object::FileKind::Elf32 => read_frames::<elf::FileHeader32<Endianness>>(&bin_data),
         _ => Err(CoredumpError::FileFormatNotSupported)?,
     }?;
-    println!("Headers: {}", notes);
-
-    let frames = symbolicate_notes(notes)?;
+    Ok((bin_data, notes))
+}
 
-    Ok(Stacktrace {
-        registers: BTreeMap::new(),
-        frames: Vec::new(),
+/// Unwinds and symbolicates a single thread's stack, starting from its
+/// captured `rip`/`rbp`.
+fn stacktrace_for_thread(
+    notes: &CoredumpNotesHeader,
+    bin_data: &[u8],
+    thread: &ThreadState,
+    debug_dirs: &[PathBuf],
+) -> Stacktrace {
+    let addrs = unwind_frame_pointers(
+        &notes.memory,
+        bin_data,
+        thread.instruction_pointer().unwrap_or(0),
+        thread.frame_pointer().unwrap_or(0),
+    );
+    let frames = symbolicate_notes(notes, &addrs, debug_dirs).unwrap_or_default();
+    Stacktrace {
+        registers: thread.as_reg_vals(),
+        frames,
         frames_omitted: None,
+    }
+}
+
+fn parse_coredump<P: AsRef<Path>>(
+    path: P,
+    debug_dirs: &[PathBuf],
+) -> Result<Stacktrace, Box<dyn Error>> {
+    let (bin_data, notes) = load_core(path)?;
+
+    let stacktrace = match notes.threads.first() {
+        Some(thread) => stacktrace_for_thread(&notes, &bin_data, thread, debug_dirs),
+        None => Stacktrace {
+            registers: BTreeMap::new(),
+            frames: Vec::new(),
+            frames_omitted: None,
+        },
+    };
+
+    Ok(stacktrace)
+}
+
+/// A loaded module referenced by an NT_FILE mapping, as surfaced in a
+/// Sentry event's image list.
+#[derive(Debug, Serialize)]
+struct ImageInfo {
+    path: String,
+    start: u64,
+    end: u64,
+    build_id: Option<String>,
+}
+
+/// A Sentry-compatible crash report: process identity, the registers and
+/// unwound stacktrace of every thread, and the list of loaded images.
+#[derive(Debug, Serialize)]
+struct CoredumpEvent {
+    command: String,
+    pid: i32,
+    registers: BTreeMap<String, RegVal>,
+    threads: Vec<Stacktrace>,
+    images: Vec<ImageInfo>,
+}
+
+/// Parses a core file and turns it into a Sentry-compatible event, ready to
+/// be serialized to JSON and uploaded to a crash-reporting backend.
+pub fn parse_coredump_event<P: AsRef<Path>>(
+    path: P,
+    debug_dirs: &[PathBuf],
+) -> Result<CoredumpEvent, Box<dyn Error>> {
+    let (bin_data, notes) = load_core(path)?;
+
+    let threads: Vec<Stacktrace> = notes
+        .threads
+        .iter()
+        .map(|thread| stacktrace_for_thread(&notes, &bin_data, thread, debug_dirs))
+        .collect();
+
+    let images = notes
+        .frames
+        .iter()
+        .map(|mapping| ImageInfo {
+            path: mapping.name.clone(),
+            start: mapping.start,
+            end: mapping.end,
+            build_id: build_id_hex(&mapping.name),
+        })
+        .collect();
+
+    let process = notes.process.clone().unwrap_or_default();
+    let registers = notes
+        .threads
+        .first()
+        .map(|thread| thread.as_reg_vals())
+        .unwrap_or_default();
+
+    Ok(CoredumpEvent {
+        command: process.command,
+        pid: process.pid,
+        registers,
+        threads,
+        images,
     })
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use std::io::Write;
+
     #[test]
     fn it_works() {
-        let coredump = parse_coredump("/home/lpraneis/personal/src/sample/core");
+        let coredump =
+            parse_coredump("/home/lpraneis/personal/src/sample/core", &default_debug_dirs());
         println!("{:?}", coredump);
     }
+
+    #[test]
+    fn hex_encode_formats_lowercase_pairs() {
+        assert_eq!(hex_encode(&[0xab, 0x01, 0xff]), "ab01ff");
+        assert_eq!(hex_encode(&[]), "");
+    }
+
+    #[test]
+    fn build_id_debug_path_splits_first_byte_as_the_directory() {
+        let build_id = [0xab, 0xcd, 0xef, 0x01];
+        let path = build_id_debug_path(Path::new("/usr/lib/debug"), &build_id).unwrap();
+        assert_eq!(
+            path,
+            PathBuf::from("/usr/lib/debug/.build-id/ab/cdef01.debug")
+        );
+    }
+
+    #[test]
+    fn build_id_debug_path_rejects_an_empty_build_id() {
+        assert!(build_id_debug_path(Path::new("/usr/lib/debug"), &[]).is_none());
+    }
+
+    #[test]
+    fn maybe_decompress_passes_through_uncompressed_data() {
+        let data = b"not compressed".to_vec();
+        assert_eq!(maybe_decompress(data.clone()).unwrap(), data);
+    }
+
+    #[test]
+    fn maybe_decompress_inflates_gzip() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"hello core").unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(maybe_decompress(compressed).unwrap(), b"hello core");
+    }
+
+    #[test]
+    fn maybe_decompress_inflates_zlib() {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"hello core").unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(maybe_decompress(compressed).unwrap(), b"hello core");
+    }
+
+    #[test]
+    fn maybe_decompress_fails_on_truncated_gzip() {
+        let result = maybe_decompress(vec![0x1f, 0x8b, 0x00, 0x00]);
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<CoredumpError>(),
+            Some(CoredumpError::DecompressionFailed)
+        ));
+    }
+
+    #[test]
+    fn core_memory_read_u64_zero_fills_past_file_size() {
+        let memory = CoreMemory {
+            segments: vec![MemorySegment {
+                vaddr: 0x1000,
+                file_offset: 0,
+                file_size: 4,
+                mem_size: 16,
+            }],
+            endian: Endianness::Little,
+        };
+        // Only the first 4 bytes come from the file; the rest is BSS.
+        let core_data = [0x01, 0x02, 0x03, 0x04];
+
+        assert_eq!(
+            memory.read_u64(&core_data, 0x1000),
+            Some(0x0000_0000_0403_0201)
+        );
+    }
+
+    #[test]
+    fn core_memory_read_u64_returns_none_outside_any_segment() {
+        let memory = CoreMemory {
+            segments: vec![MemorySegment {
+                vaddr: 0x1000,
+                file_offset: 0,
+                file_size: 8,
+                mem_size: 8,
+            }],
+            endian: Endianness::Little,
+        };
+        assert_eq!(memory.read_u64(&[0; 8], 0x2000), None);
+    }
+
+    #[test]
+    fn unwind_frame_pointers_stops_at_a_null_rbp() {
+        // rbp -> saved_rbp = 0, return_addr = 0xdead
+        let core_data: [u8; 16] = [
+            0, 0, 0, 0, 0, 0, 0, 0, // saved rbp = 0
+            0xad, 0xde, 0, 0, 0, 0, 0, 0, // return addr = 0xdead
+        ];
+        let memory = CoreMemory {
+            segments: vec![MemorySegment {
+                vaddr: 0x1000,
+                file_offset: 0,
+                file_size: core_data.len() as u64,
+                mem_size: core_data.len() as u64,
+            }],
+            endian: Endianness::Little,
+        };
+
+        let frames = unwind_frame_pointers(&memory, &core_data, 0x4000, 0x1000);
+        assert_eq!(frames, vec![0x4000, 0xdead]);
+    }
+
+    #[test]
+    fn unwind_frame_pointers_stops_on_a_non_increasing_rbp_cycle() {
+        // rbp at 0x1000 points back at itself as its own saved rbp.
+        let core_data: [u8; 16] = [
+            0x00, 0x10, 0, 0, 0, 0, 0, 0, // saved rbp = 0x1000 (no progress)
+            0xad, 0xde, 0, 0, 0, 0, 0, 0, // return addr = 0xdead
+        ];
+        let memory = CoreMemory {
+            segments: vec![MemorySegment {
+                vaddr: 0x1000,
+                file_offset: 0,
+                file_size: core_data.len() as u64,
+                mem_size: core_data.len() as u64,
+            }],
+            endian: Endianness::Little,
+        };
+
+        let frames = unwind_frame_pointers(&memory, &core_data, 0x4000, 0x1000);
+        assert_eq!(frames, vec![0x4000, 0xdead]);
+    }
 }